@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use futures::{stream::BoxStream, StreamExt};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::{Topic, TopicManager};
+
+/// Groups the inner topic's items into batches of up to `max_items`.
+///
+/// A batch is emitted as soon as it is full or `max_wait` after its first item, whichever comes first, and a partial
+/// batch is flushed when the inner topic ends. Errors are forwarded immediately without flushing the pending batch.
+pub struct Batch<T, S> {
+    inner: T,
+    max_items: usize,
+    max_wait: Duration,
+    marker: std::marker::PhantomData<S>,
+}
+
+impl<T, S> Batch<T, S> {
+    pub fn new(inner: T, max_items: usize, max_wait: Duration) -> Self {
+        assert!(max_items > 0);
+
+        Self {
+            inner,
+            max_items,
+            max_wait,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T, S> Clone for Batch<T, S>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.inner.clone(), self.max_items, self.max_wait)
+    }
+}
+
+impl<T, S> Topic<S> for Batch<T, S>
+where
+    T: Topic<S> + Clone + Send + Sync + 'static,
+    T::Output: Send + Sync + Clone + 'static,
+    T::Error: Send + Sync + Clone + 'static,
+    S: Send + Sync + 'static,
+{
+    type Output = Vec<T::Output>;
+
+    type Error = T::Error;
+
+    fn topic(&self) -> String {
+        format!("{}, batch: {} / {:?}", self.inner.topic(), self.max_items, self.max_wait)
+    }
+
+    fn init(&self, manager: &TopicManager<S>, _cancel: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
+        let mut inner = manager.topic(self.inner.clone());
+        let max_items = self.max_items;
+        let max_wait = self.max_wait;
+
+        let stream = async_stream::stream! {
+            let mut batch = Vec::with_capacity(max_items);
+            let mut deadline = None;
+
+            loop {
+                let next = match deadline {
+                    Some(deadline) => tokio::select! {
+                        next = inner.next() => Some(next),
+                        _ = tokio::time::sleep_until(deadline) => None,
+                    },
+                    None => Some(inner.next().await),
+                };
+
+                match next {
+                    Some(Some(Ok(output))) => {
+                        if batch.is_empty() {
+                            deadline = Some(Instant::now() + max_wait);
+                        }
+                        batch.push(output);
+
+                        if batch.len() >= max_items {
+                            deadline = None;
+                            yield Ok(std::mem::replace(&mut batch, Vec::with_capacity(max_items)));
+                        }
+                    }
+                    Some(Some(Err(err))) => yield Err(err),
+                    Some(None) => {
+                        if !batch.is_empty() {
+                            yield Ok(batch);
+                        }
+                        break;
+                    }
+                    None => {
+                        deadline = None;
+                        yield Ok(std::mem::replace(&mut batch, Vec::with_capacity(max_items)));
+                    }
+                }
+            }
+        };
+
+        stream.boxed()
+    }
+}
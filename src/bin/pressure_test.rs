@@ -2,6 +2,7 @@ use std::{convert::Infallible, time::Duration};
 
 use futures::{stream::BoxStream, StreamExt};
 use helium::{Interval, Topic, TopicManager};
+use tokio_util::sync::CancellationToken;
 
 #[tokio::main]
 async fn main() {
@@ -84,7 +85,7 @@ impl Topic<()> for CpuPressureTestTopic {
         self.name.clone()
     }
 
-    fn init(&self, manager: &TopicManager<()>) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
+    fn init(&self, manager: &TopicManager<()>, _cancel: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
         let interval = manager.topic(Interval::new(Duration::from_micros(1)));
         interval
             .map(|_| {
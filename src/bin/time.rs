@@ -8,6 +8,7 @@ use futures::{
     StreamExt,
 };
 use helium::{Interval, Topic, TopicManager};
+use tokio_util::sync::CancellationToken;
 
 #[tokio::main]
 async fn main() {
@@ -70,7 +71,7 @@ where
         format!("{:?}", self.dur)
     }
 
-    fn init(&self, manager: &TopicManager<S>) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
+    fn init(&self, manager: &TopicManager<S>, _cancel: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
         let interval1 = manager.topic(Interval::new(self.dur));
         let interval2 = manager.topic(Interval::new(self.dur));
         select_all(vec![interval1, interval2]).boxed()
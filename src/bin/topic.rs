@@ -1,5 +1,6 @@
 use futures::{channel::mpsc, stream::BoxStream, SinkExt, StreamExt};
 use helium::{Topic, TopicManager};
+use tokio_util::sync::CancellationToken;
 
 #[tokio::main]
 async fn main() {
@@ -46,7 +47,7 @@ where
         self.name.clone()
     }
 
-    fn init(&self, _manager: &TopicManager<S>) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
+    fn init(&self, _manager: &TopicManager<S>, _cancel: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
         let (mut tx, rx) = mpsc::channel(32);
 
         tokio::spawn(async move {
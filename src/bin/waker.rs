@@ -2,6 +2,7 @@ use std::convert::Infallible;
 
 use futures::{stream::BoxStream, StreamExt};
 use helium::{Topic, TopicManager};
+use tokio_util::sync::CancellationToken;
 
 #[tokio::main]
 async fn main() {
@@ -54,7 +55,7 @@ where
         "public".to_string()
     }
 
-    fn init(&self, _: &TopicManager<S>) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
+    fn init(&self, _: &TopicManager<S>, _cancel: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
         async_stream::stream! {
             let mut tick = false;
             loop {
@@ -1,25 +1,62 @@
 use std::{
-    collections::HashMap,
-    sync::atomic::{AtomicUsize, Ordering},
+    collections::{hash_map::Entry, HashMap},
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
     task::{Context, Poll, Waker},
+    time::Duration,
 };
 
 use futures::{Stream, StreamExt};
 use parking_lot::Mutex;
+use tokio::time::Sleep;
+
+type Slot<T> = Mutex<Option<(u64, T)>>;
+
+/// How the producer reacts to subscribers falling behind, see
+/// [`SharedStream::with_backpressure`](crate::SharedStream::with_backpressure).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backpressure {
+    /// The producer keeps pulling and overwrites items slow subscribers haven't read yet.
+    #[default]
+    Overwrite,
+    /// The producer only pulls as many items as the slowest subscriber has room for. Once it has none, the producer
+    /// waits for it to read, but for no longer than `max_wait`, after which it overwrites anyway and stops waiting on
+    /// that subscriber until it reads again.
+    Soft { max_wait: Duration },
+    /// The producer stops pulling while the next write would overwrite an item some subscriber hasn't read yet.
+    Block,
+}
 
 pub struct SharedBuffer<S>
 where
     S: Stream + Unpin,
 {
-    stream: S,
+    // The upstream is only polled, and the buffer only written, while holding this lock. `cursor` mirrors the write
+    // position so readers can load it without taking the lock.
+    producer: Mutex<S>,
     capacity: usize,
     batch_size: usize,
+    backpressure: Backpressure,
 
-    strong: AtomicUsize,
     next_stream_id: AtomicUsize,
+    terminated: AtomicBool,
+
+    subscribers: AtomicUsize,
+    delivered: AtomicU64,
+    max_lag: AtomicUsize,
 
-    buffer: Vec<Option<S::Item>>,
-    cursor: Mutex<usize>,
+    // Each slot has its own lock so a lagging reader cloning a slot can't race the producer overwriting it. Items
+    // are stored with their sequence number, which unlike `cursor` never wraps.
+    buffer: Vec<Slot<S::Item>>,
+    cursor: AtomicUsize,
+    sequence: AtomicU64,
+
+    // The next sequence number each subscriber will read, only tracked when backpressure needs the slowest one.
+    positions: Mutex<HashMap<usize, u64>>,
+    stalled: AtomicBool,
+    // Under `Backpressure::Soft`, when the producer gives up waiting on the slowest subscriber.
+    deferred: Mutex<Option<Pin<Box<Sleep>>>>,
 
     wakers: Mutex<HashMap<usize, Waker>>,
 }
@@ -29,35 +66,49 @@ where
     S: Stream + Unpin,
     S::Item: Clone,
 {
-    pub fn new(stream: S, capacity: usize, batch_size: usize) -> Self {
+    pub fn new(stream: S, capacity: usize, batch_size: usize, backpressure: Backpressure) -> Self {
         assert!(capacity > 1);
         assert!(batch_size > 0);
         assert!(if capacity >= 3 { capacity / batch_size >= 3 } else { true });
 
         Self {
-            stream,
+            producer: Mutex::new(stream),
             capacity,
             batch_size,
+            backpressure,
 
-            strong: AtomicUsize::new(1),
             next_stream_id: AtomicUsize::new(1),
+            terminated: AtomicBool::new(false),
+
+            subscribers: AtomicUsize::new(1),
+            delivered: AtomicU64::new(0),
+            max_lag: AtomicUsize::new(0),
+
+            buffer: (0..capacity).map(|_| Mutex::new(None)).collect(),
+            cursor: AtomicUsize::new(0),
+            sequence: AtomicU64::new(0),
 
-            buffer: vec![None; capacity],
-            cursor: Mutex::new(0),
+            positions: Mutex::new(HashMap::from([(0, 0)])),
+            stalled: AtomicBool::new(false),
+            deferred: Mutex::new(None),
 
             wakers: Mutex::new(HashMap::new()),
         }
     }
 }
 
+// Must only be used while holding the producer lock.
 macro_rules! update_item {
-    ($self:ident, $cursor:ident, $item:ident) => {
-        $self.buffer[*$cursor] = Some($item);
+    ($self:ident, $item:ident) => {
+        let cursor = $self.cursor.load(Ordering::Relaxed);
+        let sequence = $self.sequence.fetch_add(1, Ordering::Relaxed);
 
-        if *$cursor >= $self.capacity - 1 {
-            *$cursor = 0;
+        *$self.buffer[cursor].lock() = Some((sequence, $item));
+
+        if cursor >= $self.capacity - 1 {
+            $self.cursor.store(0, Ordering::Release);
         } else {
-            *$cursor += 1;
+            $self.cursor.store(cursor + 1, Ordering::Release);
         }
     };
 }
@@ -67,37 +118,219 @@ where
     S: Stream + Unpin,
     S::Item: Clone,
 {
-    pub fn poll_receive(&mut self, cx: &mut Context<'_>, stream_cursor: usize, stream_id: usize) -> Poll<Option<S::Item>> {
-        if stream_cursor == self.cursor() {
-            if let Some(mut cursor) = self.cursor.try_lock() {
-                let mut idx = 0;
+    pub fn poll_receive(&self, cx: &mut Context<'_>, stream_cursor: usize, stream_id: usize) -> Poll<Option<(u64, S::Item)>> {
+        let cursor = self.cursor();
 
-                while let Poll::Ready(Some(item)) = self.stream.poll_next_unpin(cx) {
-                    update_item!(self, cursor, item);
+        if stream_cursor == cursor {
+            if self.backpressure != Backpressure::Overwrite {
+                // A stalled producer is released by another subscriber reading, which only wakes registered wakers.
+                self.insert_waker(stream_id, cx.waker());
+            }
 
-                    idx += 1;
-                    if idx >= self.batch_size {
-                        break;
-                    }
-                }
+            if self.fill(cx, self.batch_size) {
+                return Poll::Ready(self.deliver(stream_cursor, stream_id));
+            }
 
-                if stream_cursor != *cursor {
-                    self.wake_all();
-                    return Poll::Ready(self.buffer[stream_cursor].clone());
-                }
+            self.insert_waker(stream_id, cx.waker());
+
+            // Another subscriber may have filled, or seen the upstream end, after the cursor was loaded above but
+            // before the waker was registered, in which case its wake-up was missed. `terminated` is loaded first as
+            // it's only set once the last item has been written.
+            let terminated = self.is_terminated();
+
+            if self.cursor() != stream_cursor {
+                return Poll::Ready(self.deliver(stream_cursor, stream_id));
             }
 
-            self.insert_waker(stream_id, cx.waker().clone());
+            if terminated {
+                return Poll::Ready(None);
+            }
 
             Poll::Pending
         } else {
-            Poll::Ready(self.buffer[stream_cursor].clone())
+            self.max_lag.fetch_max(self.lag(stream_cursor), Ordering::Relaxed);
+            Poll::Ready(self.deliver(stream_cursor, stream_id))
+        }
+    }
+
+    fn deliver(&self, stream_cursor: usize, stream_id: usize) -> Option<(u64, S::Item)> {
+        let item = self.slot(stream_cursor);
+        if let Some((sequence, _)) = &item {
+            self.delivered.fetch_add(1, Ordering::Relaxed);
+            self.track(stream_id, sequence + 1);
         }
+        item
+    }
+
+    /// Records the next sequence number `stream_id` will read, releasing the producer if it was waiting on it.
+    pub fn track(&self, stream_id: usize, next_sequence: u64) {
+        if self.backpressure == Backpressure::Overwrite {
+            return;
+        }
+
+        let mut positions = self.positions.lock();
+        positions.insert(stream_id, next_sequence);
+
+        if self.stalled.swap(false, Ordering::Relaxed) {
+            drop(positions);
+            self.wake_all();
+        }
+    }
+
+    /// Records where a new subscriber starting at `stream_cursor` will read from.
+    pub fn track_cursor(&self, stream_id: usize, stream_cursor: usize) {
+        let next_sequence = match self.slot(stream_cursor) {
+            Some((sequence, _)) if stream_cursor != self.cursor() => sequence,
+            _ => self.produced(),
+        };
+        self.track(stream_id, next_sequence);
+    }
+
+    /// Scales down how many items the producer pulls at once according to how far the slowest subscriber lags.
+    ///
+    /// Must only be called while holding the producer lock.
+    fn pull_limit(&self, cx: &mut Context<'_>, limit: usize) -> usize {
+        if self.backpressure == Backpressure::Overwrite {
+            return limit;
+        }
+
+        let mut positions = self.positions.lock();
+        let produced = self.produced();
+        let Some(mut headroom) = self.headroom(&positions, produced) else {
+            return limit;
+        };
+
+        if let Backpressure::Soft { max_wait } = self.backpressure {
+            let mut deferred = self.deferred.lock();
+
+            if headroom > 0 {
+                *deferred = None;
+            } else if deferred
+                .get_or_insert_with(|| Box::pin(tokio::time::sleep(max_wait)))
+                .as_mut()
+                .poll(cx)
+                .is_ready()
+            {
+                *deferred = None;
+
+                // Stop waiting on the subscribers holding the producer back, they are about to be lapped. Reading
+                // again tracks them anew.
+                positions.retain(|_, next| ((produced - *next) as usize) < self.capacity - 1);
+                headroom = self.headroom(&positions, produced).unwrap_or(limit);
+            }
+        }
+
+        if headroom == 0 {
+            // Set before `positions` is released, so that a subscriber tracking a read right after is sure to see it
+            // and wake whoever is waiting on the producer.
+            self.stalled.store(true, Ordering::Relaxed);
+        }
+
+        limit.min(headroom)
+    }
+
+    /// How many items can be written before the slowest tracked subscriber is lapped, `None` if none are tracked.
+    fn headroom(&self, positions: &HashMap<usize, u64>, produced: u64) -> Option<usize> {
+        let slowest = positions.values().min()?;
+
+        // One slot is kept free: a subscriber whose cursor equals the producer's reads as caught up, not lapped.
+        let unread = (produced - slowest) as usize;
+        Some((self.capacity - 1).saturating_sub(unread))
+    }
+
+    /// Takes every item between `stream_cursor` and the producer's cursor, accounted for as if read one at a time.
+    /// Returns them along with the cursor the stream ends up at.
+    pub fn drain(&self, mut stream_cursor: usize, stream_id: usize) -> (usize, Vec<S::Item>) {
+        let cursor = self.cursor();
+        let lag = self.lag(stream_cursor);
+        self.max_lag.fetch_max(lag, Ordering::Relaxed);
+
+        let mut items = Vec::with_capacity(lag);
+        let mut next_sequence = None;
+
+        while stream_cursor != cursor {
+            if let Some((sequence, item)) = self.slot(stream_cursor) {
+                items.push(item);
+                next_sequence = Some(sequence + 1);
+            }
+            stream_cursor = if stream_cursor >= self.capacity - 1 { 0 } else { stream_cursor + 1 };
+        }
+
+        self.delivered.fetch_add(items.len() as u64, Ordering::Relaxed);
+        if let Some(next_sequence) = next_sequence {
+            self.track(stream_id, next_sequence);
+        }
+
+        (stream_cursor, items)
+    }
+
+    /// How many items sit between `stream_cursor` and the producer's cursor.
+    #[inline]
+    pub fn lag(&self, stream_cursor: usize) -> usize {
+        let cursor = self.cursor();
+
+        if cursor >= stream_cursor {
+            cursor - stream_cursor
+        } else {
+            self.capacity - stream_cursor + cursor
+        }
+    }
+
+    /// Pulls up to `limit` items from upstream and registers `cx` to be woken when the cursor next moves.
+    pub fn poll_fill(&self, cx: &mut Context<'_>, stream_id: usize, limit: usize) {
+        // Registered first so that progress made by this very call also wakes the caller to re-check.
+        self.insert_waker(stream_id, cx.waker());
+        self.fill(cx, limit.min(self.batch_size));
+    }
+
+    fn fill(&self, cx: &mut Context<'_>, limit: usize) -> bool {
+        if self.is_terminated() {
+            return false;
+        }
+
+        if let Some(mut stream) = self.producer.try_lock() {
+            let start = self.cursor();
+            let limit = self.pull_limit(cx, limit);
+            let mut idx = 0;
+
+            while idx < limit {
+                match stream.poll_next_unpin(cx) {
+                    Poll::Ready(Some(item)) => {
+                        update_item!(self, item);
+                        idx += 1;
+                    }
+                    Poll::Ready(None) => {
+                        self.terminated.store(true, Ordering::Release);
+                        self.wake_all();
+                        break;
+                    }
+                    Poll::Pending => break,
+                }
+            }
+
+            if start != self.cursor() {
+                self.wake_all();
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Whether the upstream stream has ended. Subscribers still drain what is buffered before seeing `None`.
+    #[inline]
+    pub fn is_terminated(&self) -> bool {
+        self.terminated.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    pub fn slot(&self, cursor: usize) -> Option<(u64, S::Item)> {
+        self.buffer[cursor].lock().clone()
     }
 
     #[inline]
     pub fn cursor(&self) -> usize {
-        unsafe { *self.cursor.data_ptr() }
+        self.cursor.load(Ordering::Acquire)
     }
 
     pub fn capacity(&self) -> usize {
@@ -105,15 +338,26 @@ where
     }
 
     #[inline]
-    pub fn insert(&mut self, item: S::Item) {
-        let mut cursor = self.cursor.lock();
-        update_item!(self, cursor, item);
+    pub fn insert(&self, item: S::Item) {
+        let producer = self.producer.lock();
+        update_item!(self, item);
+        drop(producer);
         self.wake_all()
     }
 
     #[inline]
-    fn insert_waker(&self, stream_id: usize, waker: Waker) {
-        self.wakers.lock().insert(stream_id, waker);
+    fn insert_waker(&self, stream_id: usize, waker: &Waker) {
+        // One slot per subscriber, only re-cloned when the subscriber is polled from a different task.
+        match self.wakers.lock().entry(stream_id) {
+            Entry::Occupied(mut entry) => {
+                if !entry.get().will_wake(waker) {
+                    entry.insert(waker.clone());
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(waker.clone());
+            }
+        }
     }
 
     #[inline]
@@ -132,29 +376,65 @@ where
 {
     #[inline]
     pub fn new_stream_cursor(&self) -> usize {
-        let cursor = self.cursor();
-        let cursor = if cursor == 0 { self.capacity - 1 } else { cursor - 1 };
-        if self.buffer[cursor].is_none() {
+        let cursor = self.latest_cursor();
+        if self.buffer[cursor].lock().is_none() {
             self.cursor()
         } else {
             cursor
         }
     }
 
+    pub fn subscribers(&self) -> usize {
+        self.subscribers.load(Ordering::Relaxed)
+    }
+
+    pub fn produced(&self) -> u64 {
+        self.sequence.load(Ordering::Relaxed)
+    }
+
+    pub fn delivered(&self) -> u64 {
+        self.delivered.load(Ordering::Relaxed)
+    }
+
+    pub fn max_lag(&self) -> usize {
+        self.max_lag.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn latest(&self) -> Option<S::Item> {
+        self.slot(self.latest_cursor()).map(|(_, item)| item)
+    }
+
+    #[inline]
+    fn latest_cursor(&self) -> usize {
+        let cursor = self.cursor();
+        if cursor == 0 {
+            self.capacity - 1
+        } else {
+            cursor - 1
+        }
+    }
+
     #[inline]
     pub fn new_stream_id(&self) -> usize {
-        self.strong.fetch_add(1, Ordering::AcqRel);
+        self.subscribers.fetch_add(1, Ordering::Relaxed);
         self.next_stream_id.fetch_add(1, Ordering::Relaxed)
     }
 
+    /// Stops counting `stream_id` as a subscriber, for a handle that is kept around but never read from. Detached
+    /// streams must not call `drop_stream`.
+    pub fn detach_stream(&self, stream_id: usize) {
+        self.subscribers.fetch_sub(1, Ordering::Relaxed);
+        if self.backpressure != Backpressure::Overwrite {
+            self.positions.lock().remove(&stream_id);
+        }
+    }
+
     #[inline]
-    pub fn drop_stream(&mut self, stream_id: usize) {
-        if self.strong.fetch_sub(1, Ordering::AcqRel) == 1 {
-            std::thread::yield_now();
-            if self.strong.load(Ordering::Acquire) == 0 {
-                unsafe { std::ptr::drop_in_place(self) };
-                return;
-            }
+    pub fn drop_stream(&self, stream_id: usize) {
+        self.subscribers.fetch_sub(1, Ordering::Relaxed);
+        if self.backpressure != Backpressure::Overwrite {
+            self.positions.lock().remove(&stream_id);
         }
         self.wakers.lock().remove(&stream_id);
         self.wake_all();
@@ -0,0 +1,77 @@
+use std::{convert::Infallible, sync::Arc};
+
+use futures::{
+    channel::mpsc::{self, Receiver, Sender},
+    stream::BoxStream,
+    StreamExt,
+};
+use parking_lot::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use crate::{Backpressure, Topic, TopicManager};
+
+/// A topic fed from outside through the `Sender` returned by [`Channel::new`].
+///
+/// The topic's buffer blocks rather than overwrites, see [`Backpressure::Block`], so every subscriber sees every
+/// item sent through the `Sender`, and senders wait once `buffer` items are queued behind the slowest subscriber.
+/// A subscriber that stops reading therefore holds up both the senders and every other subscriber.
+///
+/// Values pushed with [`TopicManager::publish`] are the exception: `publish` can't wait, so it writes straight into
+/// the buffer and may overwrite items a slow subscriber hasn't read yet.
+///
+/// The receiving half is consumed by the first `init`; once the topic has been evicted, a fresh `Channel`
+/// has to be created to feed it again.
+pub struct Channel<T, S> {
+    name: String,
+    receiver: Arc<Mutex<Option<Receiver<T>>>>,
+    marker: std::marker::PhantomData<S>,
+}
+
+impl<T, S> Channel<T, S> {
+    pub fn new(name: impl Into<String>, buffer: usize) -> (Self, Sender<T>) {
+        let (tx, rx) = mpsc::channel(buffer);
+
+        let channel = Self {
+            name: name.into(),
+            receiver: Arc::new(Mutex::new(Some(rx))),
+            marker: std::marker::PhantomData,
+        };
+
+        (channel, tx)
+    }
+}
+
+impl<T, S> Clone for Channel<T, S> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            receiver: self.receiver.clone(),
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T, S> Topic<S> for Channel<T, S>
+where
+    T: Send + Sync + 'static,
+    S: Send + Sync + 'static,
+{
+    type Output = T;
+
+    type Error = Infallible;
+
+    fn topic(&self) -> String {
+        self.name.clone()
+    }
+
+    fn backpressure(&self) -> Backpressure {
+        Backpressure::Block
+    }
+
+    fn init(&self, _manager: &TopicManager<S>, _cancel: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
+        match self.receiver.lock().take() {
+            Some(rx) => rx.map(Ok).boxed(),
+            None => futures::stream::empty().boxed(),
+        }
+    }
+}
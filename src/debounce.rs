@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use futures::{stream::BoxStream, StreamExt};
+use tokio_util::sync::CancellationToken;
+
+use crate::{Topic, TopicManager};
+
+/// Emits the latest item of the inner topic once it has been quiet for `dur`.
+///
+/// Errors are debounced like any other item. When the inner topic ends, a pending item is flushed immediately.
+pub struct Debounce<T, S> {
+    inner: T,
+    dur: Duration,
+    marker: std::marker::PhantomData<S>,
+}
+
+impl<T, S> Debounce<T, S> {
+    pub fn new(inner: T, dur: Duration) -> Self {
+        Self {
+            inner,
+            dur,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T, S> Clone for Debounce<T, S>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.inner.clone(), self.dur)
+    }
+}
+
+impl<T, S> Topic<S> for Debounce<T, S>
+where
+    T: Topic<S> + Clone + Send + Sync + 'static,
+    T::Output: Send + Sync + Clone + 'static,
+    T::Error: Send + Sync + Clone + 'static,
+    S: Send + Sync + 'static,
+{
+    type Output = T::Output;
+
+    type Error = T::Error;
+
+    fn topic(&self) -> String {
+        format!("{}, debounce: {:?}", self.inner.topic(), self.dur)
+    }
+
+    fn init(&self, manager: &TopicManager<S>, _cancel: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
+        let mut inner = manager.topic(self.inner.clone());
+        let dur = self.dur;
+
+        let stream = async_stream::stream! {
+            let mut pending = None;
+
+            loop {
+                let Some(item) = pending.take() else {
+                    match inner.next().await {
+                        Some(item) => pending = Some(item),
+                        None => break,
+                    }
+                    continue;
+                };
+
+                let next = tokio::select! {
+                    next = inner.next() => Some(next),
+                    _ = tokio::time::sleep(dur) => None,
+                };
+
+                match next {
+                    Some(Some(next)) => pending = Some(next),
+                    Some(None) => {
+                        yield item;
+                        break;
+                    }
+                    None => yield item,
+                }
+            }
+        };
+
+        stream.boxed()
+    }
+}
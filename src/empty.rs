@@ -1,6 +1,7 @@
 use std::convert::Infallible;
 
 use futures::{stream::BoxStream, StreamExt};
+use tokio_util::sync::CancellationToken;
 
 use crate::{Topic, TopicManager};
 
@@ -16,6 +17,12 @@ impl<T, S> EmptyTopic<T, S> {
     }
 }
 
+impl<T, S> Clone for EmptyTopic<T, S> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
 impl<T, S> Default for EmptyTopic<T, S> {
     fn default() -> Self {
         Self::new()
@@ -31,7 +38,7 @@ where
 
     type Error = Infallible;
 
-    fn init(&self, _: &TopicManager<S>) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
+    fn init(&self, _: &TopicManager<S>, _cancel: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
         futures::stream::empty().boxed()
     }
 }
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use futures::{stream::BoxStream, StreamExt};
+use tokio_util::sync::CancellationToken;
+
+use crate::{Topic, TopicManager};
+
+pub struct Filter<T, F, S> {
+    inner: T,
+    label: String,
+    predicate: Arc<F>,
+    marker: std::marker::PhantomData<S>,
+}
+
+impl<T, F, S> Filter<T, F, S> {
+    pub fn new(inner: T, label: impl Into<String>, predicate: F) -> Self {
+        Self {
+            inner,
+            label: label.into(),
+            predicate: Arc::new(predicate),
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T, F, S> Clone for Filter<T, F, S>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            label: self.label.clone(),
+            predicate: self.predicate.clone(),
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T, F, S> Topic<S> for Filter<T, F, S>
+where
+    T: Topic<S> + Clone + Send + Sync + 'static,
+    T::Output: Send + Sync + Clone + 'static,
+    T::Error: Send + Sync + Clone + 'static,
+    F: Fn(&T::Output) -> bool + Send + Sync + 'static,
+    S: Send + Sync + 'static,
+{
+    type Output = T::Output;
+
+    type Error = T::Error;
+
+    fn topic(&self) -> String {
+        format!("{:?} => {:?}", self.inner.topic(), self.label)
+    }
+
+    fn init(&self, manager: &TopicManager<S>, _cancel: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
+        let predicate = self.predicate.clone();
+
+        manager
+            .topic(self.inner.clone())
+            .filter(move |item| futures::future::ready(item.as_ref().map_or(true, |output| predicate(output))))
+            .boxed()
+    }
+}
@@ -1,11 +1,21 @@
+mod batch;
 mod buffer;
+mod channel;
+mod debounce;
 mod empty;
+mod filter;
+mod map;
+mod merge;
+mod retry;
 mod routes;
+mod sink;
 mod stream;
+mod throttle;
 mod time;
 mod topic;
 
-pub use {empty::*, routes::*, stream::*, time::*, topic::*};
+pub use buffer::Backpressure;
+pub use {batch::*, channel::*, debounce::*, empty::*, filter::*, map::*, merge::*, retry::*, routes::*, sink::*, stream::*, throttle::*, time::*, topic::*};
 
 pub(crate) static mut GLOBAL_CAPACITY: usize = 128;
 pub(crate) static mut GLOBAL_BATCH_SIZE: usize = 16;
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use futures::{stream::BoxStream, StreamExt};
+use tokio_util::sync::CancellationToken;
+
+use crate::{Topic, TopicManager};
+
+pub struct Map<T, F, S> {
+    inner: T,
+    label: String,
+    f: Arc<F>,
+    marker: std::marker::PhantomData<S>,
+}
+
+impl<T, F, S> Map<T, F, S> {
+    pub fn new(inner: T, label: impl Into<String>, f: F) -> Self {
+        Self {
+            inner,
+            label: label.into(),
+            f: Arc::new(f),
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T, F, S> Clone for Map<T, F, S>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            label: self.label.clone(),
+            f: self.f.clone(),
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T, F, O, S> Topic<S> for Map<T, F, S>
+where
+    T: Topic<S> + Clone + Send + Sync + 'static,
+    T::Output: Send + Sync + Clone + 'static,
+    T::Error: Send + Sync + Clone + 'static,
+    F: Fn(T::Output) -> O + Send + Sync + 'static,
+    O: Send + 'static,
+    S: Send + Sync + 'static,
+{
+    type Output = O;
+
+    type Error = T::Error;
+
+    fn topic(&self) -> String {
+        format!("{:?} => {:?}", self.inner.topic(), self.label)
+    }
+
+    fn init(&self, manager: &TopicManager<S>, _cancel: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
+        let f = self.f.clone();
+
+        manager.topic(self.inner.clone()).map(move |item| item.map(|output| f(output))).boxed()
+    }
+}
@@ -0,0 +1,60 @@
+use futures::{
+    future::Either,
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{Topic, TopicManager};
+
+pub struct Merge<A, B, S> {
+    a: A,
+    b: B,
+    marker: std::marker::PhantomData<S>,
+}
+
+impl<A, B, S> Merge<A, B, S> {
+    pub fn new(a: A, b: B) -> Self {
+        Self {
+            a,
+            b,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<A, B, S> Clone for Merge<A, B, S>
+where
+    A: Clone,
+    B: Clone,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.a.clone(), self.b.clone())
+    }
+}
+
+impl<A, B, S> Topic<S> for Merge<A, B, S>
+where
+    A: Topic<S> + Clone + Send + Sync + 'static,
+    A::Output: Send + Sync + Clone + 'static,
+    A::Error: Send + Sync + Clone + 'static,
+    B: Topic<S> + Clone + Send + Sync + 'static,
+    B::Output: Send + Sync + Clone + 'static,
+    B::Error: Send + Sync + Clone + 'static,
+    S: Send + Sync + 'static,
+{
+    type Output = Either<A::Output, B::Output>;
+
+    type Error = Either<A::Error, B::Error>;
+
+    fn topic(&self) -> String {
+        format!("({:?}, {:?})", self.a.topic(), self.b.topic())
+    }
+
+    fn init(&self, manager: &TopicManager<S>, _cancel: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
+        let a = manager.topic(self.a.clone()).map(|item| item.map(Either::Left).map_err(Either::Left));
+        let b = manager.topic(self.b.clone()).map(|item| item.map(Either::Right).map_err(Either::Right));
+
+        stream::select(a, b).boxed()
+    }
+}
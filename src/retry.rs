@@ -0,0 +1,92 @@
+use std::{sync::Arc, time::Duration};
+
+use futures::{stream::BoxStream, StreamExt};
+use tokio_util::sync::CancellationToken;
+
+use crate::{Topic, TopicManager};
+
+/// Re-runs the inner topic's `init` after `backoff` whenever it yields an error.
+///
+/// The retry budget is reset by every successful item. Once `max_retries` consecutive errors have been
+/// retried, the next error is forwarded to subscribers and the stream ends.
+pub struct Retry<T, S> {
+    inner: Arc<T>,
+    max_retries: usize,
+    backoff: Duration,
+    marker: std::marker::PhantomData<S>,
+}
+
+impl<T, S> Retry<T, S> {
+    pub fn new(inner: T, max_retries: usize, backoff: Duration) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            max_retries,
+            backoff,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T, S> Clone for Retry<T, S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            max_retries: self.max_retries,
+            backoff: self.backoff,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T, S> Topic<S> for Retry<T, S>
+where
+    T: Topic<S> + Send + Sync + 'static,
+    T::Output: Send + 'static,
+    T::Error: Send + 'static,
+    S: Send + Sync + 'static,
+{
+    type Output = T::Output;
+
+    type Error = T::Error;
+
+    fn topic(&self) -> String {
+        format!("{}, retries: {}, backoff: {:?}", self.inner.topic(), self.max_retries, self.backoff)
+    }
+
+    fn init(&self, manager: &TopicManager<S>, cancel: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
+        let inner = self.inner.clone();
+        let manager = manager.clone();
+        let max_retries = self.max_retries;
+        let backoff = self.backoff;
+
+        let stream = async_stream::stream! {
+            let mut retries = 0;
+            let mut stream = inner.init(&manager, cancel.clone());
+
+            while let Some(item) = stream.next().await {
+                match item {
+                    Ok(output) => {
+                        retries = 0;
+                        yield Ok(output);
+                    }
+                    Err(_) if retries < max_retries => {
+                        retries += 1;
+
+                        tokio::select! {
+                            _ = tokio::time::sleep(backoff) => {}
+                            _ = cancel.cancelled() => break,
+                        }
+
+                        stream = inner.init(&manager, cancel.clone());
+                    }
+                    Err(err) => {
+                        yield Err(err);
+                        break;
+                    }
+                }
+            }
+        };
+
+        stream.boxed()
+    }
+}
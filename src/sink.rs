@@ -0,0 +1,47 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use futures::{Sink, SinkExt};
+use tokio::sync::Mutex;
+
+/// A cloneable handle letting many tasks write `Item`s into one sink.
+///
+/// Every call holds the sink for its whole duration, so items sent from different clones are never interleaved.
+pub struct SharedSink<Si, Item> {
+    sink: Arc<Mutex<Si>>,
+    marker: PhantomData<fn(Item)>,
+}
+
+impl<Si, Item> SharedSink<Si, Item> {
+    pub fn new(sink: Si) -> Self {
+        Self {
+            sink: Arc::new(Mutex::new(sink)),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<Si, Item> Clone for SharedSink<Si, Item> {
+    fn clone(&self) -> Self {
+        Self {
+            sink: self.sink.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<Si, Item> SharedSink<Si, Item>
+where
+    Si: Sink<Item> + Unpin,
+{
+    pub async fn send(&self, item: Item) -> Result<(), Si::Error> {
+        self.sink.lock().await.send(item).await
+    }
+
+    pub async fn flush(&self) -> Result<(), Si::Error> {
+        self.sink.lock().await.flush().await
+    }
+
+    pub async fn close(&self) -> Result<(), Si::Error> {
+        self.sink.lock().await.close().await
+    }
+}
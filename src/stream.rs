@@ -1,21 +1,31 @@
 use std::{
+    fmt,
+    future::Future,
     pin::Pin,
-    sync::atomic::{AtomicPtr, Ordering},
-    task::{Context, Poll},
+    sync::Arc,
+    task::{ready, Context, Poll},
+    time::Duration,
 };
 
-use futures::Stream;
+use futures::{Stream, StreamExt};
+use tokio::time::Sleep;
 
-use crate::buffer::SharedBuffer;
+use crate::buffer::{Backpressure, SharedBuffer};
 
+/// A stream whose items are shared by all of its clones, each of which reads them at its own pace.
+///
+/// `SharedStream` is `Send` and `Sync` whenever the upstream stream and its items are `Send`: the upstream is only
+/// polled under the buffer's producer lock, every slot is read and written under its own lock, and the producer
+/// cursor is an atomic. The buffer is freed once the last clone is dropped.
 pub struct SharedStream<S>
 where
     S: Stream + Unpin,
     S::Item: Clone,
 {
-    buffer: AtomicPtr<SharedBuffer<S>>,
+    buffer: Arc<SharedBuffer<S>>,
     cursor: usize,
     stream_id: usize,
+    detached: bool,
 }
 
 impl<S> SharedStream<S>
@@ -24,15 +34,134 @@ where
     S::Item: Clone,
 {
     pub fn new(stream: S, capacity: usize, batch_size: usize) -> Self {
+        Self::with_backpressure(stream, capacity, batch_size, Backpressure::Overwrite)
+    }
+
+    /// Like `new`, but the producer stops pulling from `stream` rather than overwrite an item that any subscriber
+    /// hasn't read yet, so no subscriber ever misses an item.
+    ///
+    /// The flip side is that a single subscriber that stops reading stalls every other one. Items pushed with
+    /// `insert` bypass this and may still overwrite unread ones.
+    pub fn bounded_blocking(stream: S, capacity: usize, batch_size: usize) -> Self {
+        Self::with_backpressure(stream, capacity, batch_size, Backpressure::Block)
+    }
+
+    /// Like `new`, with `backpressure` deciding what the producer does when subscribers fall behind.
+    ///
+    /// [`Backpressure::Soft`] holds the producer back to the pace of the slowest subscriber like
+    /// [`Backpressure::Block`], but a subscriber that stops reading delays the others by `max_wait` once rather than
+    /// stalling them. The producer then stops waiting on it until it reads again.
+    pub fn with_backpressure(stream: S, capacity: usize, batch_size: usize, backpressure: Backpressure) -> Self {
         Self {
-            buffer: AtomicPtr::new(Box::into_raw(Box::new(SharedBuffer::new(stream, capacity, batch_size)))),
+            buffer: Arc::new(SharedBuffer::new(stream, capacity, batch_size, backpressure)),
             cursor: 0,
             stream_id: 0,
+            detached: false,
         }
     }
 
-    pub fn insert(&mut self, item: S::Item) {
-        self.buffer_mut().insert(item);
+    /// Writes `item` after everything produced so far, without waiting for room even under
+    /// [`Backpressure::Block`], so it may overwrite items a slow subscriber hasn't read yet.
+    pub fn insert(&self, item: S::Item) {
+        self.buffer.insert(item);
+    }
+
+    /// Returns a new subscriber that only receives items produced after this call.
+    ///
+    /// Unlike `clone`, which starts at the most recently produced item and so replays it first, this skips
+    /// everything already in the buffer.
+    pub fn subscribe_latest(&self) -> Self {
+        let stream = Self {
+            buffer: self.buffer.clone(),
+            cursor: self.buffer.cursor(),
+            stream_id: self.buffer.new_stream_id(),
+            detached: false,
+        };
+        stream.buffer.track_cursor(stream.stream_id, stream.cursor);
+        stream
+    }
+
+    /// Returns how many items are buffered between this stream's cursor and the producer's.
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.lag(self.cursor)
+    }
+
+    /// Waits until at least `n` items are buffered ahead of this stream's cursor, pulling from upstream if needed.
+    /// Resolves early if the upstream stream ends.
+    ///
+    /// `n` is capped at `capacity - 1`, the most items a consumer can lag behind before the producer laps it.
+    pub async fn wait_for_buffered(&mut self, n: usize) {
+        let n = n.min(self.buffer.capacity() - 1);
+
+        futures::future::poll_fn(|cx| {
+            let buffered = self.buffered_len();
+            if buffered >= n || self.buffer.is_terminated() {
+                return Poll::Ready(());
+            }
+
+            let stream_id = self.stream_id;
+            self.buffer.poll_fill(cx, stream_id, n - buffered);
+
+            if self.buffered_len() >= n || self.buffer.is_terminated() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Takes every item already buffered ahead of this stream's cursor, without waiting or polling the upstream.
+    ///
+    /// Only this stream's cursor moves. Concurrent clones each drain from their own cursor, so they may see
+    /// different ranges depending on when they call this.
+    pub fn drain_ready(&mut self) -> Vec<S::Item> {
+        let (cursor, items) = self.buffer.drain(self.cursor, self.stream_id);
+        self.cursor = cursor;
+        items
+    }
+
+    /// Pairs every item with its sequence number: its position among all items ever produced by the upstream or
+    /// inserted, starting at 0 and never wrapping.
+    ///
+    /// A gap between consecutive sequence numbers means the items in between were overwritten before this stream
+    /// got to read them.
+    pub fn enumerated(self) -> Enumerated<S> {
+        Enumerated { stream: self }
+    }
+
+    /// Returns the most recently produced item without moving this stream's cursor.
+    pub fn latest(&self) -> Option<S::Item> {
+        self.buffer.latest()
+    }
+
+    /// Moves this stream's cursor so that the next item it reads is the one with sequence number `sequence`, as
+    /// numbered by [`SharedStream::enumerated`].
+    ///
+    /// Seeking to the number of items produced so far waits for the next one. An item `capacity` or more behind the
+    /// producer can't be read back even if its slot hasn't been overwritten yet, since the producer's cursor is back
+    /// on that slot and a consumer there reads as caught up.
+    pub fn seek_to(&mut self, sequence: u64) -> Result<(), SeekError> {
+        let produced = self.buffer.produced();
+        let capacity = self.buffer.capacity();
+
+        if sequence > produced {
+            return Err(SeekError::NotProduced);
+        }
+
+        let cursor = (sequence % capacity as u64) as usize;
+
+        if sequence < produced {
+            let buffered = produced - sequence < capacity as u64;
+            if !buffered || self.buffer.slot(cursor).map(|(slot, _)| slot) != Some(sequence) {
+                return Err(SeekError::Evicted);
+            }
+        }
+
+        self.cursor = cursor;
+        self.buffer.track(self.stream_id, sequence);
+
+        Ok(())
     }
 }
 
@@ -41,37 +170,233 @@ where
     S: Stream + Unpin,
     S::Item: Clone,
 {
-    fn buffer(&self) -> &SharedBuffer<S> {
-        unsafe { &**self.buffer.as_ptr() }
+    /// Reads the counters shared by every clone of this stream.
+    pub fn metrics(&self) -> SharedStreamMetrics {
+        SharedStreamMetrics {
+            subscribers: self.buffer.subscribers(),
+            produced: self.buffer.produced(),
+            delivered: self.buffer.delivered(),
+            max_lag: self.buffer.max_lag(),
+        }
+    }
+
+    fn poll_next_enumerated(&mut self, cx: &mut Context<'_>) -> Poll<Option<(u64, S::Item)>> {
+        let poll = self.buffer.poll_receive(cx, self.cursor, self.stream_id);
+
+        if let Poll::Ready(Some(_)) = poll {
+            self.advance();
+        }
+
+        poll
     }
 
-    fn buffer_mut(&mut self) -> &mut SharedBuffer<S> {
-        unsafe { &mut **self.buffer.as_ptr() }
+    fn advance(&mut self) {
+        self.cursor += 1;
+        if self.cursor >= self.buffer.capacity() {
+            self.cursor = 0;
+        }
+    }
+
+    /// Snapshots the cursors of this stream and its producer without taking any lock.
+    pub fn debug_state(&self) -> SharedStreamState {
+        SharedStreamState {
+            producer_cursor: self.buffer.cursor(),
+            consumer_cursor: self.cursor,
+            capacity: self.buffer.capacity(),
+            buffered: self.buffered_len(),
+        }
+    }
+
+    /// The buffer shared by every clone, for writing to it without holding on to a subscriber.
+    pub(crate) fn buffer(&self) -> Arc<SharedBuffer<S>> {
+        self.buffer.clone()
+    }
+
+    /// Keeps this handle from counting as a subscriber or holding back the producer, for handles that only keep the
+    /// buffer alive and are never read from.
+    pub(crate) fn detach(&mut self) {
+        if !self.detached {
+            self.detached = true;
+            self.buffer.detach_stream(self.stream_id);
+        }
     }
 }
 
-impl<S> Clone for SharedStream<S>
+impl<F, S> SharedStream<Resubscribe<F, S>>
 where
+    F: Fn() -> S + Unpin,
     S: Stream + Unpin,
     S::Item: Clone,
 {
-    fn clone(&self) -> Self {
+    /// Builds a shared stream whose upstream is recreated by `factory` every time it ends, so subscribers never see
+    /// the end of the stream.
+    ///
+    /// Items already buffered stay readable across the switch and the new upstream's items follow them. Errors
+    /// carried as items don't end the upstream and are forwarded like any other item.
+    ///
+    /// An upstream that ends without producing anything, such as a failed connection, is only recreated after a
+    /// 100ms delay so that a factory that keeps failing doesn't spin. See `from_factory_with_delay`.
+    pub fn from_factory(factory: F, capacity: usize, batch_size: usize) -> Self {
+        Self::from_factory_with_delay(factory, RESUBSCRIBE_DELAY, capacity, batch_size)
+    }
+
+    /// Like `from_factory`, waiting `delay` before recreating an upstream that ended without producing anything.
+    pub fn from_factory_with_delay(factory: F, delay: Duration, capacity: usize, batch_size: usize) -> Self {
+        Self::new(Resubscribe::with_delay(factory, delay), capacity, batch_size)
+    }
+}
+
+const RESUBSCRIBE_DELAY: Duration = Duration::from_millis(100);
+
+pub struct Resubscribe<F, S> {
+    factory: F,
+    stream: S,
+    delay: Duration,
+    produced: bool,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl<F, S> Resubscribe<F, S>
+where
+    F: Fn() -> S,
+{
+    pub fn new(factory: F) -> Self {
+        Self::with_delay(factory, RESUBSCRIBE_DELAY)
+    }
+
+    pub fn with_delay(factory: F, delay: Duration) -> Self {
+        let stream = factory();
         Self {
-            buffer: AtomicPtr::new(self.buffer.load(Ordering::Relaxed)),
-            cursor: self.buffer().new_stream_cursor(),
-            stream_id: self.buffer().new_stream_id(),
+            factory,
+            stream,
+            delay,
+            produced: false,
+            sleep: None,
         }
     }
 }
 
+impl<F, S> Stream for Resubscribe<F, S>
+where
+    F: Fn() -> S + Unpin,
+    S: Stream + Unpin,
+{
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(sleep) = &mut self.sleep {
+                ready!(sleep.as_mut().poll(cx));
+                self.sleep = None;
+                self.stream = (self.factory)();
+            }
+
+            match self.stream.poll_next_unpin(cx) {
+                Poll::Ready(Some(item)) => {
+                    self.produced = true;
+                    return Poll::Ready(Some(item));
+                }
+                // An upstream that produced something is replaced straight away, one that didn't only after `delay`.
+                Poll::Ready(None) if self.produced => {
+                    self.produced = false;
+                    self.stream = (self.factory)();
+                }
+                Poll::Ready(None) => self.sleep = Some(Box::pin(tokio::time::sleep(self.delay))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+pub struct Enumerated<S>
+where
+    S: Stream + Unpin,
+    S::Item: Clone,
+{
+    stream: SharedStream<S>,
+}
+
+impl<S> Stream for Enumerated<S>
+where
+    S: Stream + Unpin,
+    S::Item: Clone,
+{
+    type Item = (u64, S::Item);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.stream.poll_next_enumerated(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekError {
+    /// The item has already been overwritten, or is too far behind the producer to be read.
+    Evicted,
+    /// The sequence number is past the next item the upstream will produce.
+    NotProduced,
+}
+
+impl fmt::Display for SeekError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeekError::Evicted => f.write_str("item is no longer buffered"),
+            SeekError::NotProduced => f.write_str("item has not been produced yet"),
+        }
+    }
+}
+
+impl std::error::Error for SeekError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SharedStreamMetrics {
+    /// Live clones of the stream, not counting the handle a `TopicManager` keeps for each topic.
+    pub subscribers: usize,
+    /// Items produced by the upstream or inserted.
+    pub produced: u64,
+    /// Items handed out, summed over every subscriber.
+    pub delivered: u64,
+    /// The furthest any subscriber has been behind the producer when reading.
+    pub max_lag: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SharedStreamState {
+    pub producer_cursor: usize,
+    pub consumer_cursor: usize,
+    pub capacity: usize,
+    pub buffered: usize,
+}
+
+impl<S> Clone for SharedStream<S>
+where
+    S: Stream + Unpin,
+    S::Item: Clone,
+{
+    fn clone(&self) -> Self {
+        let stream = Self {
+            buffer: self.buffer.clone(),
+            cursor: self.buffer.new_stream_cursor(),
+            stream_id: self.buffer.new_stream_id(),
+            detached: false,
+        };
+        stream.buffer.track_cursor(stream.stream_id, stream.cursor);
+        stream
+    }
+}
+
 impl<S> Drop for SharedStream<S>
 where
     S: Stream + Unpin,
     S::Item: Clone,
 {
     fn drop(&mut self) {
-        let stream_id = self.stream_id;
-        self.buffer_mut().drop_stream(stream_id);
+        if !self.detached {
+            self.buffer.drop_stream(self.stream_id);
+        }
     }
 }
 
@@ -83,33 +408,10 @@ where
     type Item = S::Item;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let cursor = self.cursor;
-        let stream_id = self.stream_id;
-
-        let poll = self.buffer_mut().poll_receive(cx, cursor, stream_id);
-
-        if poll.is_ready() {
-            self.cursor += 1;
-            if self.cursor >= self.buffer().capacity() {
-                self.cursor = 0;
-            }
-        }
-
-        poll
+        self.poll_next_enumerated(cx).map(|item| item.map(|(_, item)| item))
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let buffer = self.buffer();
-
-        if buffer.cursor() == self.cursor {
-            (0, None)
-        } else {
-            let len = if buffer.cursor() > self.cursor {
-                buffer.cursor() - self.cursor
-            } else {
-                buffer.capacity() - self.cursor + buffer.cursor()
-            };
-            (len, None)
-        }
+        (self.buffered_len(), None)
     }
 }
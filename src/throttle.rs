@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use futures::{stream::BoxStream, StreamExt};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::{Topic, TopicManager};
+
+/// Passes at most one item of the inner topic per `dur`.
+///
+/// The first item of a window is emitted right away and any further items within that window are dropped rather
+/// than coalesced. Errors are always forwarded and don't count against the window.
+pub struct Throttle<T, S> {
+    inner: T,
+    dur: Duration,
+    marker: std::marker::PhantomData<S>,
+}
+
+impl<T, S> Throttle<T, S> {
+    pub fn new(inner: T, dur: Duration) -> Self {
+        Self {
+            inner,
+            dur,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T, S> Clone for Throttle<T, S>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.inner.clone(), self.dur)
+    }
+}
+
+impl<T, S> Topic<S> for Throttle<T, S>
+where
+    T: Topic<S> + Clone + Send + Sync + 'static,
+    T::Output: Send + Sync + Clone + 'static,
+    T::Error: Send + Sync + Clone + 'static,
+    S: Send + Sync + 'static,
+{
+    type Output = T::Output;
+
+    type Error = T::Error;
+
+    fn topic(&self) -> String {
+        format!("{}, throttle: {:?}", self.inner.topic(), self.dur)
+    }
+
+    fn init(&self, manager: &TopicManager<S>, _cancel: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
+        let dur = self.dur;
+        let mut window: Option<Instant> = None;
+
+        manager
+            .topic(self.inner.clone())
+            .filter(move |item| {
+                let pass = match item {
+                    Ok(_) => {
+                        let now = Instant::now();
+                        if window.is_none_or(|start| now.duration_since(start) >= dur) {
+                            window = Some(now);
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                    Err(_) => true,
+                };
+                futures::future::ready(pass)
+            })
+            .boxed()
+    }
+}
@@ -4,16 +4,25 @@ use std::{
 };
 
 use futures::{stream::BoxStream, StreamExt};
+use tokio_util::sync::CancellationToken;
 
 use crate::{Topic, TopicManager};
 
+#[derive(Clone)]
 pub struct Interval {
     dur: Duration,
+    initial: Duration,
 }
 
 impl Interval {
+    /// Ticks immediately, then every `dur`.
     pub fn new(dur: Duration) -> Self {
-        Self { dur }
+        Self::with_initial_delay(Duration::ZERO, dur)
+    }
+
+    /// Ticks after `initial`, then every `period`.
+    pub fn with_initial_delay(initial: Duration, period: Duration) -> Self {
+        Self { dur: period, initial }
     }
 }
 
@@ -26,18 +35,33 @@ where
     type Error = Infallible;
 
     fn topic(&self) -> String {
-        format!("{:?}", self.dur)
+        if self.initial.is_zero() {
+            format!("{:?}", self.dur)
+        } else {
+            format!("{:?}, initial: {:?}", self.dur, self.initial)
+        }
     }
 
-    fn init(&self, _manager: &TopicManager<S>) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
+    fn init(&self, _manager: &TopicManager<S>, cancel: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
         let dur = self.dur;
+        let initial = self.initial;
 
         let stream = async_stream::stream! {
+            if !initial.is_zero() {
+                tokio::select! {
+                    _ = tokio::time::sleep(initial) => {}
+                    _ = cancel.cancelled() => return,
+                }
+            }
+
             let mut ins = Instant::now();
             loop {
                 yield Ok(ins);
                 ins = Instant::now();
-                tokio::time::sleep(dur).await;
+                tokio::select! {
+                    _ = tokio::time::sleep(dur) => {}
+                    _ = cancel.cancelled() => break,
+                }
             }
         };
 
@@ -45,6 +69,7 @@ where
     }
 }
 
+#[derive(Clone)]
 pub struct Timeout {
     dur: Duration,
 }
@@ -67,13 +92,15 @@ where
         format!("{:?}", self.dur)
     }
 
-    fn init(&self, _manager: &TopicManager<S>) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
+    fn init(&self, _manager: &TopicManager<S>, cancel: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
         let dur = self.dur;
 
         let stream = async_stream::stream! {
             let ins = Instant::now();
-            tokio::time::sleep(dur).await;
-            yield Ok(ins);
+            tokio::select! {
+                _ = tokio::time::sleep(dur) => yield Ok(ins),
+                _ = cancel.cancelled() => {}
+            }
         };
 
         stream.boxed()
@@ -1,17 +1,19 @@
 use std::{
-    any::Any,
+    any::{Any, TypeId},
     collections::HashMap,
     ops::{Deref, DerefMut},
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 
 use futures::{stream::BoxStream, Stream, StreamExt};
 use parking_lot::Mutex;
 use tokio::task::JoinSet;
+use tokio_util::sync::{CancellationToken, DropGuard};
 
-use crate::{stream::SharedStream, GLOBAL_BATCH_SIZE, GLOBAL_CAPACITY};
+use crate::{stream::SharedStream, Backpressure, Filter, Map, Merge, GLOBAL_BATCH_SIZE, GLOBAL_CAPACITY};
 
 #[derive(Debug)]
 pub struct TopicManager<S>
@@ -19,7 +21,8 @@ where
     S: Send + Sync + 'static,
 {
     store: Arc<S>,
-    topics: Arc<Mutex<HashMap<String, Box<dyn Any + Send + Sync>>>>,
+    topics: Arc<Mutex<TopicMap>>,
+    error_handlers: Arc<Mutex<HashMap<TopicId, Box<dyn Any + Send + Sync>>>>,
 }
 
 impl<S> Clone for TopicManager<S>
@@ -30,6 +33,7 @@ where
         Self {
             store: self.store.clone(),
             topics: self.topics.clone(),
+            error_handlers: self.error_handlers.clone(),
         }
     }
 }
@@ -42,9 +46,11 @@ where
         Self {
             store: Arc::new(store),
             topics: Arc::new(Mutex::new(HashMap::new())),
+            error_handlers: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
+    /// Subscribes to `topic`, returning the concrete [`TopicToken`]. See [`TopicManager::subscribe`].
     pub fn topic<T>(&self, topic: T) -> TopicToken<T, S>
     where
         T: Topic<S> + Send + Sync + 'static,
@@ -54,13 +60,181 @@ where
         TopicToken::new(topic, self.clone())
     }
 
+    /// Subscribes to `topic`, sharing the buffer of any live subscription to an equal topic and only running
+    /// `init` when there is none.
+    ///
+    /// The returned stream owns its subscription and doesn't borrow the manager, so it can be moved into a spawned
+    /// task. It starts at the topic's most recent item, if any. Once every subscriber has been dropped the topic is
+    /// evicted and its source stream dropped.
+    pub fn subscribe<T>(&self, topic: T) -> impl Stream<Item = Result<T::Output, T::Error>> + Send + Unpin + 'static
+    where
+        T: Topic<S> + Send + Sync + 'static,
+        T::Output: Send + Sync + Clone + 'static,
+        T::Error: Send + Sync + Clone + 'static,
+    {
+        self.topic(topic)
+    }
+
     pub fn store(&self) -> &S {
         &self.store
     }
 
     pub(crate) fn topics(&self) -> Vec<String> {
-        self.topics.lock().iter().map(|(k, _)| k.clone()).collect()
+        self.topics.lock().keys().map(|(_, name)| name.clone()).collect()
+    }
+
+    /// Pushes `value` to every current subscriber of `topic`, alongside whatever its `init` stream produces.
+    ///
+    /// Returns `false` without doing anything when the topic has no live subscribers.
+    ///
+    /// The value is written into the buffer without waiting, so it ignores [`Backpressure::Block`] and may overwrite
+    /// items a slow subscriber hasn't read yet, even on a topic such as [`Channel`](crate::Channel) that blocks.
+    pub fn publish<T>(&self, topic: T, value: T::Output) -> bool
+    where
+        T: Topic<S> + Send + Sync + 'static,
+        T::Output: Send + Sync + Clone + 'static,
+        T::Error: Send + Sync + Clone + 'static,
+    {
+        let topic_id = topic_id::<T, S>(&topic);
+
+        // The topics lock is released before inserting: `insert` waits on the producer lock, whose holder may itself
+        // need the topics lock to evict an inner topic that has ended.
+        let buffer = self
+            .topics
+            .lock()
+            .get(&topic_id)
+            .and_then(|topic| topic.downcast_ref::<TopicToken<T, S>>())
+            .map(|token| token.buffer());
+
+        match buffer {
+            Some(buffer) => {
+                buffer.insert(Ok(value));
+                true
+            }
+            None => false,
+        }
     }
+
+    /// Returns the last value emitted on `topic`, or `None` if it has no subscribers, has not emitted yet, or its last
+    /// item was an error.
+    ///
+    /// This only peeks at the shared buffer; no subscriber's cursor is advanced.
+    pub fn latest<T>(&self, topic: T) -> Option<T::Output>
+    where
+        T: Topic<S> + Send + Sync + 'static,
+        T::Output: Send + Sync + Clone + 'static,
+        T::Error: Send + Sync + Clone + 'static,
+    {
+        let topic_id = topic_id::<T, S>(&topic);
+
+        let topics = self.topics.lock();
+
+        topics
+            .get(&topic_id)
+            .and_then(|topic| topic.downcast_ref::<TopicToken<T, S>>())
+            .and_then(|token| token.latest())
+            .and_then(Result::ok)
+    }
+
+    /// Registers how errors yielded by `topic`'s stream are handled, replacing any previous handler.
+    ///
+    /// Topics without a handler forward their errors to subscribers, as [`ErrorAction::Forward`] does.
+    pub fn on_error<T, F>(&self, topic: T, handler: F)
+    where
+        T: Topic<S> + 'static,
+        T::Error: 'static,
+        F: Fn(&T::Error) -> ErrorAction + Send + Sync + 'static,
+    {
+        let handler: ErrorHandler<T::Error> = Arc::new(handler);
+        self.error_handlers.lock().insert(topic_id::<T, S>(&topic), Box::new(handler));
+    }
+
+    fn supervise<T>(&self, topic: T, topic_id: TopicId, cancel: CancellationToken) -> BoxStream<'static, Result<T::Output, T::Error>>
+    where
+        T: Topic<S> + Send + Sync + 'static,
+        T::Output: Send + 'static,
+        T::Error: Send + 'static,
+    {
+        let mut stream = topic.init(self, cancel.clone());
+        let manager = self.clone();
+
+        let stream = async_stream::stream! {
+            let mut restarts = 0;
+
+            while let Some(item) = stream.next().await {
+                let Err(err) = &item else {
+                    restarts = 0;
+                    yield item;
+                    continue;
+                };
+
+                let action = manager
+                    .error_handlers
+                    .lock()
+                    .get(&topic_id)
+                    .and_then(|handler| handler.downcast_ref::<ErrorHandler<T::Error>>())
+                    .map(|handler| handler(err))
+                    .unwrap_or_default();
+
+                match action {
+                    ErrorAction::Forward => yield item,
+                    ErrorAction::Skip => {}
+                    ErrorAction::Restart if restarts < MAX_RESTARTS => {
+                        restarts += 1;
+
+                        tokio::select! {
+                            _ = tokio::time::sleep(RESTART_BACKOFF) => {}
+                            _ = cancel.cancelled() => break,
+                        }
+
+                        stream = topic.init(&manager, cancel.clone());
+                    }
+                    ErrorAction::Restart | ErrorAction::Terminate => {
+                        yield item;
+                        break;
+                    }
+                }
+            }
+        };
+
+        stream.boxed()
+    }
+}
+
+/// What the manager does with an error yielded by a topic's stream, see [`TopicManager::on_error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorAction {
+    /// Pass the error on to subscribers and keep the stream running.
+    #[default]
+    Forward,
+    /// Drop the error without subscribers seeing it.
+    Skip,
+    /// Drop the error and replace the stream by running the topic's `init` again after a short backoff.
+    ///
+    /// After five restarts without a successful item in between, the error is passed on to subscribers
+    /// and the topic ends instead, as with [`Retry`](crate::Retry).
+    Restart,
+    /// Pass the error on to subscribers, then end the topic.
+    Terminate,
+}
+
+const MAX_RESTARTS: usize = 5;
+const RESTART_BACKOFF: Duration = Duration::from_millis(50);
+
+type ErrorHandler<E> = Arc<dyn Fn(&E) -> ErrorAction + Send + Sync>;
+
+/// Topics are keyed on their concrete type as well as their name, so that two topic types whose names happen to
+/// format the same never share a buffer.
+type TopicId = (TypeId, String);
+
+type TopicMap = HashMap<TopicId, Box<dyn Any + Send + Sync>>;
+
+fn topic_id<T, S>(topic: &T) -> TopicId
+where
+    T: Topic<S> + 'static,
+    S: Send + Sync + 'static,
+{
+    (TypeId::of::<T>(), format!("{} {{ {} }}", std::any::type_name::<T>(), topic.topic()))
 }
 
 pub struct TopicToken<T, S>
@@ -70,10 +244,11 @@ where
     T::Error: Send + Sync + Clone + 'static,
     S: Send + Sync + 'static,
 {
-    topic_id: String,
+    topic_id: TopicId,
     stream: SharedStream<BoxStream<'static, Result<T::Output, T::Error>>>,
     manager: TopicManager<S>,
-    strong: Arc<()>,
+    // Cancels the token handed to `init` once the last token for this instance, including the manager's, is gone.
+    strong: Arc<DropGuard>,
 }
 
 impl<T, S> TopicToken<T, S>
@@ -86,7 +261,7 @@ where
     pub fn new(topic: T, manager: TopicManager<S>) -> Self {
         let topics = manager.topics.lock();
 
-        let topic_id = format!("{} {{ {} }}", std::any::type_name::<T>(), topic.topic());
+        let topic_id = topic_id::<T, S>(&topic);
 
         let token = if let Some(topic) = topics.get(&topic_id) {
             if let Some(topic) = topic.downcast_ref::<Self>() {
@@ -97,14 +272,22 @@ where
         } else {
             drop(topics);
 
+            let capacity = topic.capacity();
+            let batch_size = topic.batch_size();
+            let backpressure = topic.backpressure();
+            let cancel = CancellationToken::new();
+
             let token = Self {
                 topic_id: topic_id.clone(),
-                stream: SharedStream::new(topic.init(&manager), topic.capacity(), topic.batch_size()),
+                stream: SharedStream::with_backpressure(manager.supervise(topic, topic_id.clone(), cancel.clone()), capacity, batch_size, backpressure),
                 manager: manager.clone(),
-                strong: Arc::new(()),
+                strong: Arc::new(cancel.drop_guard()),
             };
 
-            manager.topics.lock().insert(topic_id, Box::new(token.clone()));
+            // The manager's handle is never read from, so it mustn't count as a subscriber.
+            let mut cached = token.clone();
+            cached.stream.detach();
+            manager.topics.lock().insert(topic_id, Box::new(cached));
 
             token
         };
@@ -112,6 +295,15 @@ where
         token
     }
 
+    /// Whether the manager's entry for `topic_id` is still the instance `strong` belongs to, rather than one
+    /// created after it was evicted.
+    fn is_current(topics: &TopicMap, topic_id: &TopicId, strong: &Arc<DropGuard>) -> bool {
+        topics
+            .get(topic_id)
+            .and_then(|topic| topic.downcast_ref::<Self>())
+            .is_some_and(|topic| Arc::ptr_eq(&topic.strong, strong))
+    }
+
     pub fn spawn(mut self) -> JoinSet<()> {
         let mut join_set = JoinSet::new();
         join_set.spawn(async move { while let Some(_s) = self.next().await {} });
@@ -150,7 +342,7 @@ where
             let topics = self.manager.topics.clone();
             tokio::spawn(async move {
                 let mut lock = topics.lock();
-                if Arc::strong_count(&strong) == 2 {
+                if Arc::strong_count(&strong) == 2 && Self::is_current(&lock, &topic_id, &strong) {
                     lock.remove(&topic_id);
                 }
             });
@@ -194,7 +386,18 @@ where
     type Item = Result<T::Output, T::Error>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        self.stream.poll_next_unpin(cx)
+        let poll = self.stream.poll_next_unpin(cx);
+
+        if let Poll::Ready(None) = poll {
+            let mut lock = self.manager.topics.lock();
+            if Self::is_current(&lock, &self.topic_id, &self.strong) {
+                let topic = lock.remove(&self.topic_id);
+                drop(lock);
+                drop(topic);
+            }
+        }
+
+        poll
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
@@ -214,7 +417,14 @@ where
         Default::default()
     }
 
-    fn init(&self, manager: &TopicManager<S>) -> BoxStream<'static, Result<Self::Output, Self::Error>>;
+    /// Creates the source stream shared by every subscriber of this topic.
+    ///
+    /// When the stream ends, subscribers finish once they have read everything still buffered, and the topic is
+    /// evicted from the manager so that the next subscription runs `init` again.
+    ///
+    /// `cancel` is cancelled once the topic has been evicted and its last subscriber dropped. Streams holding
+    /// resources beyond their own lifetime can select on it to release them promptly.
+    fn init(&self, manager: &TopicManager<S>, cancel: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>>;
 
     fn capacity(&self) -> usize {
         unsafe { GLOBAL_CAPACITY }
@@ -223,6 +433,41 @@ where
     fn batch_size(&self) -> usize {
         unsafe { GLOBAL_BATCH_SIZE }
     }
+
+    /// What the shared buffer does when subscribers fall behind. Defaults to overwriting unread items.
+    fn backpressure(&self) -> Backpressure {
+        Backpressure::Overwrite
+    }
+
+    /// Combines this topic with `other` into one topic emitting both outputs as an `Either`.
+    fn merge<B>(self, other: B) -> Merge<Self, B, S>
+    where
+        Self: Sized,
+    {
+        Merge::new(self, other)
+    }
+
+    /// Transforms every output with `f`, once in the shared producer rather than per subscriber.
+    ///
+    /// `label` is appended to this topic's key so that different transforms of the same source don't share a buffer.
+    fn map<F, O>(self, label: impl Into<String>, f: F) -> Map<Self, F, S>
+    where
+        Self: Sized,
+        F: Fn(Self::Output) -> O,
+    {
+        Map::new(self, label, f)
+    }
+
+    /// Only forwards outputs for which `predicate` holds, evaluated once in the shared producer. Errors always pass.
+    ///
+    /// `label` is appended to this topic's key so that different filters of the same source don't share a buffer.
+    fn filter<F>(self, label: impl Into<String>, predicate: F) -> Filter<Self, F, S>
+    where
+        Self: Sized,
+        F: Fn(&Self::Output) -> bool,
+    {
+        Filter::new(self, label, predicate)
+    }
 }
 
 #[macro_export]
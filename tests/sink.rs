@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use futures::sink;
+use helium::SharedSink;
+use parking_lot::Mutex;
+
+const FRAME_LEN: usize = 16;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_senders_write_whole_frames() {
+    let written = Arc::new(Mutex::new(Vec::new()));
+
+    // Writes a frame one byte at a time, yielding in between, so a frame could be torn if sends overlapped.
+    let out = sink::unfold(written.clone(), |written, frame: Vec<u8>| async move {
+        for byte in frame {
+            written.lock().push(byte);
+            tokio::task::yield_now().await;
+        }
+        Ok::<_, std::convert::Infallible>(written)
+    });
+
+    let shared = SharedSink::new(Box::pin(out));
+
+    let mut senders = Vec::new();
+    for id in 0..8u8 {
+        let shared = shared.clone();
+        senders.push(tokio::spawn(async move {
+            for _ in 0..50 {
+                shared.send(vec![id; FRAME_LEN]).await.unwrap();
+            }
+        }));
+    }
+
+    for sender in senders {
+        sender.await.unwrap();
+    }
+    shared.flush().await.unwrap();
+
+    let written = written.lock();
+    assert_eq!(written.len(), 8 * 50 * FRAME_LEN);
+
+    let mut counts = [0; 8];
+    for frame in written.chunks(FRAME_LEN) {
+        assert!(frame.iter().all(|byte| *byte == frame[0]), "torn frame: {frame:?}");
+        counts[frame[0] as usize] += 1;
+    }
+    assert_eq!(counts, [50; 8]);
+}
@@ -0,0 +1,241 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::Context,
+    time::{Duration, Instant},
+};
+
+use futures::{
+    channel::mpsc,
+    stream,
+    task::{self, ArcWake},
+    StreamExt,
+};
+use helium::{Backpressure, SeekError, SharedStream};
+
+#[tokio::test]
+async fn drain_ready_is_counted_in_metrics() {
+    let mut a = SharedStream::new(stream::iter(0..4u32), 8, 1);
+    let mut b = a.clone();
+
+    for _ in 0..4 {
+        a.next().await;
+    }
+
+    assert_eq!(b.drain_ready(), vec![0, 1, 2, 3]);
+
+    let metrics = a.metrics();
+    assert_eq!(metrics.subscribers, 2);
+    assert_eq!(metrics.produced, 4);
+    assert_eq!(metrics.delivered, 8);
+    assert_eq!(metrics.max_lag, 4);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn subscribers_are_woken_for_a_one_shot_source() {
+    for _ in 0..200 {
+        // Pending at first, then a single item, like a `Timeout` topic.
+        let source = stream::once(async {
+            tokio::time::sleep(Duration::from_micros(100)).await;
+            7u32
+        })
+        .boxed();
+        let shared = SharedStream::new(source, 8, 1);
+
+        let subscribers: Vec<_> = (0..8).map(|_| tokio::spawn(shared.clone().collect::<Vec<_>>())).collect();
+        drop(shared);
+
+        for subscriber in subscribers {
+            let items = tokio::time::timeout(Duration::from_secs(5), subscriber)
+                .await
+                .expect("subscriber was never woken")
+                .unwrap();
+            assert_eq!(items, vec![7]);
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_readers_never_see_torn_or_misordered_items() {
+    const ITEMS: u64 = 20_000;
+    const CAPACITY: usize = 64;
+
+    // Every item is a block of identical words, so a read racing the producer's write would show mixed values.
+    let source = stream::iter(0..ITEMS).map(|i| [i; 32]);
+    let shared = SharedStream::new(source, CAPACITY, 8);
+
+    let mut readers = Vec::new();
+    for reader in 0..8 {
+        let mut stream = shared.clone().enumerated();
+        readers.push(tokio::spawn(async move {
+            let mut last = None;
+            while let Some((sequence, item)) = stream.next().await {
+                assert!(item.iter().all(|word| *word == item[0]), "torn item: {item:?}");
+                assert_eq!(item[0], sequence);
+                assert!(last.is_none_or(|last| sequence > last), "{sequence} after {last:?}");
+                last = Some(sequence);
+
+                if (sequence + reader) % 7 == 0 {
+                    tokio::task::yield_now().await;
+                }
+            }
+            last
+        }));
+    }
+    drop(shared);
+
+    for reader in readers {
+        let last = reader.await.unwrap();
+        // A reader lapped while the source ends can't tell it fell behind, but it always stops on the slot holding
+        // the final item.
+        assert!(
+            last.is_some_and(|last| last % CAPACITY as u64 == (ITEMS - 1) % CAPACITY as u64),
+            "ended at {last:?}"
+        );
+    }
+}
+
+struct NoopWake;
+
+impl ArcWake for NoopWake {
+    fn wake_by_ref(_: &Arc<Self>) {}
+}
+
+#[test]
+fn pending_subscriber_keeps_one_waker_per_task() {
+    let mut shared = SharedStream::new(stream::pending::<u32>(), 8, 1);
+
+    // Every clone of a waker holds a reference to its `Arc`, so the strong count tracks how many are kept around.
+    let first = Arc::new(NoopWake);
+    let first_waker = task::waker(first.clone());
+    let mut cx = Context::from_waker(&first_waker);
+
+    for _ in 0..1000 {
+        assert!(shared.poll_next_unpin(&mut cx).is_pending());
+    }
+    assert_eq!(Arc::strong_count(&first), 3);
+
+    // Polled from another task, the subscriber swaps its stored waker instead of keeping both.
+    let second = Arc::new(NoopWake);
+    let second_waker = task::waker(second.clone());
+    let mut cx = Context::from_waker(&second_waker);
+
+    for _ in 0..1000 {
+        assert!(shared.poll_next_unpin(&mut cx).is_pending());
+    }
+    assert_eq!(Arc::strong_count(&first), 2);
+    assert_eq!(Arc::strong_count(&second), 3);
+}
+
+#[tokio::test]
+async fn from_factory_waits_before_recreating_an_empty_upstream() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let factory = {
+        let calls = calls.clone();
+        move || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            stream::empty::<u32>()
+        }
+    };
+
+    let mut shared = SharedStream::from_factory_with_delay(factory, Duration::from_millis(20), 8, 1);
+
+    assert!(tokio::time::timeout(Duration::from_millis(110), shared.next()).await.is_err());
+    let calls = calls.load(Ordering::SeqCst);
+    assert!((2..=7).contains(&calls), "factory called {calls} times");
+}
+
+#[tokio::test]
+async fn from_factory_recreates_an_upstream_that_produced_straight_away() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let factory = {
+        let calls = calls.clone();
+        move || stream::once(futures::future::ready(calls.fetch_add(1, Ordering::SeqCst)))
+    };
+
+    let shared = SharedStream::from_factory_with_delay(factory, Duration::from_secs(60), 8, 1);
+
+    let items = tokio::time::timeout(Duration::from_secs(5), shared.take(3).collect::<Vec<_>>()).await.unwrap();
+    assert_eq!(items, vec![0, 1, 2]);
+}
+
+#[tokio::test]
+async fn seek_to_respects_the_eviction_boundary() {
+    const CAPACITY: usize = 4;
+
+    let (tx, rx) = mpsc::unbounded();
+    let mut producer = SharedStream::new(rx, CAPACITY, 1);
+    let mut stream = producer.clone();
+
+    for i in 0..6u64 {
+        tx.unbounded_send(i).unwrap();
+        assert_eq!(producer.next().await, Some(i));
+    }
+    let produced = producer.metrics().produced;
+    assert_eq!(produced, 6);
+
+    // The slot still holds this item, but the producer's cursor is back on it.
+    assert_eq!(stream.seek_to(produced - CAPACITY as u64), Err(SeekError::Evicted));
+    assert_eq!(stream.seek_to(produced - 1 - CAPACITY as u64), Err(SeekError::Evicted));
+    assert_eq!(stream.seek_to(produced + 1), Err(SeekError::NotProduced));
+
+    assert_eq!(stream.seek_to(produced - CAPACITY as u64 + 1), Ok(()));
+    assert_eq!(stream.next().await, Some(3));
+
+    assert_eq!(stream.seek_to(produced), Ok(()));
+    assert!(futures::poll!(stream.next()).is_pending());
+
+    tx.unbounded_send(6).unwrap();
+    assert_eq!(stream.next().await, Some(6));
+}
+
+/// Runs a fast and a slow subscriber over the same source and returns how many items the slow one missed.
+async fn missed_by_slow_subscriber(backpressure: Backpressure) -> u64 {
+    const ITEMS: u64 = 200;
+
+    let shared = SharedStream::with_backpressure(stream::iter(0..ITEMS), 16, 4, backpressure);
+    let mut slow = shared.clone().enumerated();
+
+    let fast = tokio::spawn(shared.count());
+
+    let mut read = 0;
+    while slow.next().await.is_some() {
+        read += 1;
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+
+    assert_eq!(fast.await.unwrap() as u64, ITEMS);
+    ITEMS - read
+}
+
+#[tokio::test]
+async fn soft_backpressure_overwrites_less_than_overwrite() {
+    let overwritten = missed_by_slow_subscriber(Backpressure::Overwrite).await;
+    let soft = missed_by_slow_subscriber(Backpressure::Soft {
+        max_wait: Duration::from_millis(50),
+    })
+    .await;
+
+    assert!(soft < overwritten, "soft missed {soft}, overwrite missed {overwritten}");
+}
+
+#[tokio::test]
+async fn soft_backpressure_recovers_throughput_after_a_stuck_subscriber_is_lapped() {
+    let shared = SharedStream::with_backpressure(
+        stream::iter(0..2000u32),
+        16,
+        4,
+        Backpressure::Soft {
+            max_wait: Duration::from_millis(10),
+        },
+    );
+    let _stuck = shared.clone();
+
+    // Waiting `max_wait` for every batch would take about 5s; the stuck subscriber should only cost one wait.
+    let started = Instant::now();
+    let items = tokio::time::timeout(Duration::from_secs(5), shared.count()).await.unwrap();
+    assert_eq!(items, 2000);
+    assert!(started.elapsed() < Duration::from_millis(500), "took {:?}", started.elapsed());
+}
@@ -0,0 +1,328 @@
+use std::{
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures::{future::Either, stream::BoxStream, SinkExt, StreamExt};
+use helium::{Batch, Channel, ErrorAction, Retry, Topic, TopicManager};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Clone)]
+struct Numbers(u32);
+
+impl Topic<()> for Numbers {
+    type Output = u32;
+
+    type Error = Infallible;
+
+    fn topic(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn init(&self, _: &TopicManager<()>, _: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
+        futures::stream::iter((0..self.0).map(Ok)).boxed()
+    }
+}
+
+#[tokio::test]
+async fn manager_handle_is_not_counted_as_subscriber() {
+    let manager = TopicManager::new(());
+
+    let a = manager.topic(Numbers(3));
+    assert_eq!(a.metrics().subscribers, 1);
+
+    let b = manager.topic(Numbers(3));
+    assert_eq!(b.metrics().subscribers, 2);
+
+    drop(a);
+    assert_eq!(b.metrics().subscribers, 1);
+}
+
+#[tokio::test]
+async fn channel_delivers_every_item_to_a_slow_subscriber() {
+    let manager = TopicManager::new(());
+    let (channel, mut tx) = Channel::new("numbers", 4);
+
+    let fast = manager.subscribe(channel.clone());
+    let mut slow = manager.subscribe(channel);
+
+    let sender = tokio::spawn(async move {
+        for i in 0..1000u32 {
+            tx.send(i).await.unwrap();
+        }
+    });
+
+    let fast = tokio::spawn(fast.take(1000).map(Result::unwrap).collect::<Vec<_>>());
+
+    let mut received = Vec::new();
+    while received.len() < 1000 {
+        received.push(slow.next().await.unwrap().unwrap());
+        tokio::task::yield_now().await;
+    }
+
+    sender.await.unwrap();
+    assert_eq!(received, (0..1000).collect::<Vec<_>>());
+    assert_eq!(fast.await.unwrap(), (0..1000).collect::<Vec<_>>());
+}
+
+struct Finite {
+    len: u32,
+    inits: Arc<AtomicUsize>,
+}
+
+impl Topic<()> for Finite {
+    type Output = u32;
+
+    type Error = Infallible;
+
+    fn topic(&self) -> String {
+        self.len.to_string()
+    }
+
+    fn init(&self, _: &TopicManager<()>, _: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
+        self.inits.fetch_add(1, Ordering::SeqCst);
+        futures::stream::iter((0..self.len).map(Ok)).boxed()
+    }
+}
+
+#[tokio::test]
+async fn finite_source_ends_subscribers_and_restarts_on_resubscribe() {
+    let manager = TopicManager::new(());
+    let inits = Arc::new(AtomicUsize::new(0));
+    let topic = || Finite { len: 3, inits: inits.clone() };
+
+    let fast = manager.subscribe(topic());
+    let mut lagging = manager.subscribe(topic());
+
+    let fast: Vec<_> = fast.map(Result::unwrap).collect().await;
+    assert_eq!(fast, vec![0, 1, 2]);
+
+    // The source has ended and the topic been evicted, but the lagging subscriber still drains its buffer.
+    assert_eq!(lagging.next().await.map(Result::unwrap), Some(0));
+    let rest: Vec<_> = lagging.map(Result::unwrap).collect().await;
+    assert_eq!(rest, vec![1, 2]);
+    assert_eq!(inits.load(Ordering::SeqCst), 1);
+
+    let again: Vec<_> = manager.subscribe(topic()).map(Result::unwrap).collect().await;
+    assert_eq!(again, vec![0, 1, 2]);
+    assert_eq!(inits.load(Ordering::SeqCst), 2);
+}
+
+/// Yields `script(n)` from its `n`th `init`.
+struct Scripted {
+    script: fn(usize) -> Vec<Result<u32, String>>,
+    inits: Arc<AtomicUsize>,
+}
+
+impl Scripted {
+    fn new(script: fn(usize) -> Vec<Result<u32, String>>) -> (Self, Arc<AtomicUsize>) {
+        let inits = Arc::new(AtomicUsize::new(0));
+        (Self { script, inits: inits.clone() }, inits)
+    }
+}
+
+impl Clone for Scripted {
+    fn clone(&self) -> Self {
+        Self {
+            script: self.script,
+            inits: self.inits.clone(),
+        }
+    }
+}
+
+impl Topic<()> for Scripted {
+    type Output = u32;
+
+    type Error = String;
+
+    fn init(&self, _: &TopicManager<()>, _: CancellationToken) -> BoxStream<'static, Result<Self::Output, Self::Error>> {
+        let n = self.inits.fetch_add(1, Ordering::SeqCst);
+        futures::stream::iter((self.script)(n)).boxed()
+    }
+}
+
+fn ok_err_ok(_: usize) -> Vec<Result<u32, String>> {
+    vec![Ok(1), Err("boom".into()), Ok(2)]
+}
+
+async fn collect_with(action: ErrorAction, topic: Scripted) -> Vec<Result<u32, String>> {
+    let manager = TopicManager::new(());
+    manager.on_error(topic.clone(), move |_| action);
+
+    let items = manager.subscribe(topic).collect();
+    tokio::time::timeout(Duration::from_secs(5), items).await.expect("topic never ended")
+}
+
+#[tokio::test]
+async fn error_action_forward_passes_errors_on() {
+    let (topic, _) = Scripted::new(ok_err_ok);
+    assert_eq!(collect_with(ErrorAction::Forward, topic).await, vec![Ok(1), Err("boom".into()), Ok(2)]);
+}
+
+#[tokio::test]
+async fn error_action_skip_drops_errors() {
+    let (topic, _) = Scripted::new(ok_err_ok);
+    assert_eq!(collect_with(ErrorAction::Skip, topic).await, vec![Ok(1), Ok(2)]);
+}
+
+#[tokio::test]
+async fn error_action_terminate_ends_after_the_error() {
+    let (topic, _) = Scripted::new(ok_err_ok);
+    assert_eq!(collect_with(ErrorAction::Terminate, topic).await, vec![Ok(1), Err("boom".into())]);
+}
+
+#[tokio::test]
+async fn error_action_restart_reruns_init() {
+    let (topic, inits) = Scripted::new(|n| if n < 2 { vec![Err("boom".into())] } else { vec![Ok(n as u32)] });
+    assert_eq!(collect_with(ErrorAction::Restart, topic).await, vec![Ok(2)]);
+    assert_eq!(inits.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn error_action_restart_gives_up_on_a_source_that_always_fails() {
+    let (topic, inits) = Scripted::new(|_| vec![Err("boom".into())]);
+    assert_eq!(collect_with(ErrorAction::Restart, topic).await, vec![Err("boom".into())]);
+    assert_eq!(inits.load(Ordering::SeqCst), 6);
+}
+
+fn ident(value: u32) -> u32 {
+    value
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn publish_does_not_deadlock_with_an_ending_inner_topic() {
+    let topic = || Numbers(50).map("id", ident as fn(u32) -> u32);
+
+    for _ in 0..20 {
+        let manager = TopicManager::new(());
+        let subscriber = manager.subscribe(topic());
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let publisher = tokio::task::spawn_blocking({
+            let manager = manager.clone();
+            let stop = stop.clone();
+            move || {
+                while !stop.load(Ordering::SeqCst) {
+                    manager.publish(topic(), 0);
+                }
+            }
+        });
+
+        let ended = tokio::time::timeout(Duration::from_secs(5), subscriber.count()).await;
+        stop.store(true, Ordering::SeqCst);
+
+        assert!(ended.is_ok(), "subscriber never saw the mapped topic end");
+        tokio::time::timeout(Duration::from_secs(5), publisher)
+            .await
+            .expect("publisher stuck in publish")
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn merges_with_the_same_joined_names_get_their_own_buffers() {
+    let manager = TopicManager::new(());
+
+    let (xy, _xy_tx) = Channel::<u32, ()>::new("x, y", 4);
+    let (z, _z_tx) = Channel::<u32, ()>::new("z", 4);
+    let (x, _x_tx) = Channel::<u32, ()>::new("x", 4);
+    let (yz, mut yz_tx) = Channel::<u32, ()>::new("y, z", 4);
+
+    let _first = manager.subscribe(xy.merge(z));
+    let mut second = manager.subscribe(x.merge(yz));
+
+    yz_tx.send(7).await.unwrap();
+
+    let item = tokio::time::timeout(Duration::from_secs(5), second.next())
+        .await
+        .expect("merge shared another buffer");
+    assert!(matches!(item, Some(Ok(Either::Right(7)))));
+}
+
+async fn collect_retried(topic: Scripted, max_retries: usize) -> Vec<Result<u32, String>> {
+    let manager = TopicManager::new(());
+    let items = manager.subscribe(Retry::new(topic, max_retries, Duration::from_millis(1))).collect();
+    tokio::time::timeout(Duration::from_secs(5), items).await.expect("topic never ended")
+}
+
+#[tokio::test]
+async fn retry_recovers_after_errors() {
+    let (topic, inits) = Scripted::new(|n| if n < 2 { vec![Err("boom".into())] } else { vec![Ok(n as u32)] });
+    assert_eq!(collect_retried(topic, 3).await, vec![Ok(2)]);
+    assert_eq!(inits.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn retry_budget_resets_after_a_successful_item() {
+    let (topic, inits) = Scripted::new(|n| if n < 4 { vec![Ok(n as u32), Err("boom".into())] } else { vec![Ok(n as u32)] });
+    assert_eq!(collect_retried(topic, 1).await, vec![Ok(0), Ok(1), Ok(2), Ok(3), Ok(4)]);
+    assert_eq!(inits.load(Ordering::SeqCst), 5);
+}
+
+#[tokio::test]
+async fn retry_forwards_the_error_once_retries_run_out() {
+    let (topic, inits) = Scripted::new(|_| vec![Err("boom".into())]);
+    assert_eq!(collect_retried(topic, 2).await, vec![Err("boom".into())]);
+    assert_eq!(inits.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn retry_stops_backing_off_when_cancelled() {
+    let (topic, _) = Scripted::new(|_| vec![Err("boom".into())]);
+    let retry = Retry::new(topic, 3, Duration::from_secs(3600));
+
+    let manager = TopicManager::new(());
+    let cancel = CancellationToken::new();
+    let mut stream = retry.init(&manager, cancel.clone());
+
+    assert!(tokio::time::timeout(Duration::from_millis(20), stream.next()).await.is_err());
+    cancel.cancel();
+
+    let ended = tokio::time::timeout(Duration::from_secs(5), stream.next()).await.expect("still backing off");
+    assert!(ended.is_none());
+}
+
+async fn collect_batched(topic: Scripted, max_items: usize, max_wait: Duration) -> Vec<Result<Vec<u32>, String>> {
+    let manager = TopicManager::new(());
+    let items = manager.subscribe(Batch::new(topic, max_items, max_wait)).collect();
+    tokio::time::timeout(Duration::from_secs(5), items).await.expect("topic never ended")
+}
+
+#[tokio::test]
+async fn batch_flushes_full_batches() {
+    let (topic, _) = Scripted::new(|_| (0..6).map(Ok).collect());
+    let batches = collect_batched(topic, 3, Duration::from_secs(3600)).await;
+    assert_eq!(batches, vec![Ok(vec![0, 1, 2]), Ok(vec![3, 4, 5])]);
+}
+
+#[tokio::test]
+async fn batch_flushes_the_partial_batch_when_the_source_ends() {
+    let (topic, _) = Scripted::new(|_| (0..5).map(Ok).collect());
+    let batches = collect_batched(topic, 3, Duration::from_secs(3600)).await;
+    assert_eq!(batches, vec![Ok(vec![0, 1, 2]), Ok(vec![3, 4])]);
+}
+
+#[tokio::test]
+async fn batch_flushes_after_max_wait() {
+    let manager = TopicManager::new(());
+    let (channel, mut tx) = Channel::<u32, ()>::new("batched", 4);
+    let mut batches = manager.subscribe(Batch::new(channel, 10, Duration::from_millis(20)));
+
+    tx.send(1).await.unwrap();
+    tx.send(2).await.unwrap();
+
+    // The channel stays open, so only the deadline can flush the batch.
+    let batch = tokio::time::timeout(Duration::from_secs(5), batches.next()).await.expect("batch never flushed");
+    assert_eq!(batch, Some(Ok(vec![1, 2])));
+}
+
+#[tokio::test]
+async fn batch_forwards_errors_without_flushing() {
+    let (topic, _) = Scripted::new(|_| vec![Ok(1), Err("boom".into()), Ok(2)]);
+    let batches = collect_batched(topic, 10, Duration::from_secs(3600)).await;
+    assert_eq!(batches, vec![Err("boom".into()), Ok(vec![1, 2])]);
+}